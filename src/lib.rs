@@ -18,17 +18,24 @@
 //! let mut parity = UnionFind::new(N);
 //! for i in 0..N {
 //!     if i & 1 == 0 {
-//!         parity.union(0, i)
+//!         parity.union_into(0, i)
 //!     } else {
-//!         parity.union(1, i)
+//!         parity.union_into(1, i)
 //!     }
 //! };
 //! for i in 0..N {
 //!     let c = parity.find(i);
 //!     assert_eq!(c, i & 1)
 //! }
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Add, Neg, Sub};
+
 pub struct UnionFind {
-    parts: Vec<usize>
+    parts: Vec<usize>,
+    rank: Vec<usize>,
+    size: Vec<usize>,
 }
 
 impl UnionFind {
@@ -39,16 +46,64 @@ impl UnionFind {
         for i in 0..n {
             parts.push(i)
         };
-        UnionFind{ parts: parts }
+        UnionFind { parts, rank: vec![0; n], size: vec![1; n] }
+    }
+
+    /// Create a partition table of size `n + 1` for use as
+    /// an interval/next-free allocator over `0..n`: slot `n`
+    /// is a sentinel meaning "no free index remains", so
+    /// `next_free()` on a fully-consumed table returns `n`.
+    /// Running time O(n).
+    pub fn new_interval(n: usize) -> UnionFind {
+        UnionFind::new(n + 1)
+    }
+
+    /// Merge the partitions containing `i` and `j` using
+    /// union-by-rank: the root of the shallower tree is
+    /// attached under the root of the deeper one, and ties
+    /// are broken by incrementing the surviving root's
+    /// rank. This keeps trees shallow so that the amortized
+    /// running time of `find()` stays O(alpha(n)), but it
+    /// does *not* promise which of `i` or `j`'s old
+    /// canonical element survives the merge; use
+    /// `union_into()` if that guarantee is required.
+    /// Running time O(alpha(n)).
+    pub fn union(&mut self, i: usize, j: usize) {
+        let ri = self.find(i);
+        let rj = self.find(j);
+        if ri == rj {
+            return;
+        }
+        let (small, big) = if self.rank[ri] < self.rank[rj] {
+            (ri, rj)
+        } else {
+            (rj, ri)
+        };
+        self.parts[small] = big;
+        self.size[big] += self.size[small];
+        if self.rank[ri] == self.rank[rj] {
+            self.rank[big] += 1;
+        }
     }
 
     /// Merge the partitions containing `i` and `j`.  This
     /// operation is structured such that the canonical
     /// element of the merged partition will be the
     /// canonical element of `i` in the old
-    /// partition. Running time O(1).
-    pub fn union(&mut self, i: usize, j: usize) {
-        self.parts[j] = self.parts[i];
+    /// partition. This does not balance by rank, so a long
+    /// sequence of `union_into()` calls can rebuild the
+    /// chains that `union()` avoids; prefer `union()` unless
+    /// the old canonical-element guarantee is needed.
+    /// Amortized running time O(log n), from path
+    /// compression alone.
+    pub fn union_into(&mut self, i: usize, j: usize) {
+        let ri = self.find(i);
+        let rj = self.find(j);
+        if ri == rj {
+            return;
+        }
+        self.parts[rj] = ri;
+        self.size[ri] += self.size[rj];
     }
 
     /// Return a "canonical element" for the partition
@@ -97,4 +152,529 @@ impl UnionFind {
     pub fn same_only(&self, i: usize, j: usize) -> bool {
         self.find_only(i) == self.find_only(j)
     }
+
+    /// Return the number of elements in the partition
+    /// containing `i`. Time complexity is the same as
+    /// `find()`.
+    pub fn size(&mut self, i: usize) -> usize {
+        let p = self.find(i);
+        self.size[p]
+    }
+
+    /// Return every partition as a `Vec` of its member
+    /// elements, including singletons. See
+    /// `nontrivial_subsets()` for the groups-of-size->1-only
+    /// view. Running time O(n alpha(n)).
+    pub fn subsets(&mut self) -> Vec<Vec<usize>> {
+        let n = self.parts.len();
+        let mut groups: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for i in 0..n {
+            let p = self.find(i);
+            groups[p].push(i);
+        }
+        groups.into_iter().filter(|g| !g.is_empty()).collect()
+    }
+
+    /// Return every partition with more than one element, as
+    /// a `Vec` of its member elements. This is `subsets()`
+    /// with singletons dropped, for use cases like "group the
+    /// colored nodes" where unmerged elements are noise.
+    /// Running time O(n alpha(n)).
+    pub fn nontrivial_subsets(&mut self) -> Vec<Vec<usize>> {
+        self.subsets().into_iter().filter(|g| g.len() > 1).collect()
+    }
+
+    /// Return the number of distinct partitions. Running
+    /// time O(n); no path compression is needed since roots
+    /// are exactly the elements that are their own parent.
+    pub fn count(&self) -> usize {
+        self.parts.iter().enumerate().filter(|&(i, &p)| p == i).count()
+    }
+
+    /// Return the smallest index `>= i` that has not been
+    /// `consume()`d, for a table created with
+    /// `new_interval()`. This is exactly `find(i)`, reused
+    /// directly since path compression already skips
+    /// consumed indices. Running time O(alpha(n)).
+    pub fn next_free(&mut self, i: usize) -> usize {
+        self.find(i)
+    }
+
+    /// Mark `i` as consumed, so that future `next_free()`
+    /// calls on `i` (and anything unioned with it) return
+    /// the smallest free index `> i` instead. Calling this
+    /// on the sentinel index (`n`, for a table built with
+    /// `new_interval(n)`) is a no-op, since the sentinel
+    /// means "no free index remains" and can never itself be
+    /// consumed. Running time O(alpha(n)).
+    pub fn consume(&mut self, i: usize) {
+        if i + 1 >= self.parts.len() {
+            return;
+        }
+        self.union_into(i + 1, i);
+    }
+}
+
+impl IntoIterator for &mut UnionFind {
+    type Item = Vec<usize>;
+    type IntoIter = std::vec::IntoIter<Vec<usize>>;
+
+    /// Iterate over the partitions, yielding each as a
+    /// `Vec` of its member elements. Equivalent to
+    /// `subsets()`, offered for `for group in &mut uf`
+    /// ergonomics.
+    fn into_iter(self) -> Self::IntoIter {
+        self.subsets().into_iter()
+    }
+}
+
+/// A weighted (potential) union-find records not just
+/// *that* two elements are related but *how*: each element
+/// carries a value in an additive group `T`, and `union_with()`
+/// asserts a difference between two values rather than
+/// simple equivalence. This generalizes `UnionFind`, whose
+/// partitions are the special case where every difference is
+/// zero.
+///
+/// # Examples
+/// ```
+/// use union_find::WeightedUnionFind;
+/// const N: usize = 20;
+///
+/// // Record that each element is 1 greater than its
+/// // predecessor, then read back the accumulated offsets.
+/// let mut chain = WeightedUnionFind::new(N);
+/// for i in 1..N {
+///     assert!(chain.union_with(i - 1, i, 1));
+/// }
+/// for i in 0..N {
+///     assert_eq!(chain.diff(0, i), Some(i as i64));
+/// }
+/// // A conflicting constraint on already-related elements
+/// // is rejected rather than silently accepted.
+/// assert!(!chain.union_with(0, 1, 5));
+/// ```
+pub struct WeightedUnionFind<T> {
+    parts: Vec<usize>,
+    rank: Vec<usize>,
+    weight: Vec<T>,
+}
+
+impl<T> WeightedUnionFind<T>
+where
+    T: Copy + PartialEq + Default + Add<Output = T> + Sub<Output = T> + Neg<Output = T>,
+{
+    /// Create a new partition table with `n` disjoint
+    /// partitions numbered 0..`n`, each initially at offset
+    /// zero from itself. Running time O(n).
+    pub fn new(n: usize) -> WeightedUnionFind<T> {
+        let mut parts = Vec::with_capacity(n);
+        for i in 0..n {
+            parts.push(i)
+        };
+        WeightedUnionFind { parts, rank: vec![0; n], weight: vec![T::default(); n] }
+    }
+
+    /// Return a `(root, offset)` pair for `i`, where `root`
+    /// is the canonical element of `i`'s partition and
+    /// `offset` is `value(i) - value(root)`. Amortized
+    /// worst-case running time O(alpha(n)), achieved by
+    /// compressing the path from `i` to `root` and rewriting
+    /// each node's weight to its total offset from `root`.
+    pub fn find(&mut self, i: usize) -> (usize, T) {
+        let mut p = i;
+        let mut offset = T::default();
+        while self.parts[p] != p {
+            offset = offset + self.weight[p];
+            p = self.parts[p]
+        };
+        let mut s = i;
+        let mut partial = T::default();
+        while s != p {
+            let t = self.parts[s];
+            let w = self.weight[s];
+            self.parts[s] = p;
+            self.weight[s] = offset - partial;
+            partial = partial + w;
+            s = t
+        };
+        (p, offset)
+    }
+
+    /// Assert that `value(j) - value(i) == w`, merging `i`
+    /// and `j`'s partitions by rank if they are not already
+    /// related. Returns `false` if `i` and `j` are already
+    /// related with a conflicting offset, in which case the
+    /// table is left unchanged; returns `true` otherwise.
+    /// Running time O(alpha(n)).
+    pub fn union_with(&mut self, i: usize, j: usize, w: T) -> bool {
+        let (ri, oi) = self.find(i);
+        let (rj, oj) = self.find(j);
+        if ri == rj {
+            return oj - oi == w;
+        }
+        let delta = w + oi - oj;
+        if self.rank[ri] < self.rank[rj] {
+            self.parts[ri] = rj;
+            self.weight[ri] = -delta;
+        } else {
+            self.parts[rj] = ri;
+            self.weight[rj] = delta;
+            if self.rank[ri] == self.rank[rj] {
+                self.rank[ri] += 1;
+            }
+        }
+        true
+    }
+
+    /// Return `value(j) - value(i)` if `i` and `j` are in
+    /// the same partition, or `None` if they are unrelated.
+    /// Time complexity is the same as `find()`.
+    pub fn diff(&mut self, i: usize, j: usize) -> Option<T> {
+        let (ri, oi) = self.find(i);
+        let (rj, oj) = self.find(j);
+        if ri != rj {
+            return None;
+        }
+        Some(oj - oi)
+    }
+}
+
+/// A union-find over an arbitrary hashable element type `T`,
+/// rather than a fixed range `0..n`. Elements are inserted
+/// lazily: `union()`, `find()` and `same()` add any element
+/// they have not seen before as a new singleton partition, so
+/// callers never have to maintain their own index table for
+/// labels, strings or coordinates.
+///
+/// # Examples
+/// ```
+/// use union_find::UnionFindMap;
+///
+/// let mut uf = UnionFindMap::new();
+/// uf.union(&"a", &"b");
+/// uf.union(&"b", &"c");
+/// assert!(uf.same(&"a", &"c"));
+/// assert!(!uf.same(&"a", &"z"));
+/// assert_eq!(uf.find_checked(&"q"), None);
+/// ```
+pub struct UnionFindMap<T> {
+    index: HashMap<T, usize>,
+    elems: Vec<T>,
+    parts: Vec<usize>,
+    rank: Vec<usize>,
+}
+
+impl<T: Hash + Eq + Clone> UnionFindMap<T> {
+    /// Create a new, empty union-find with no elements.
+    /// Running time O(1).
+    pub fn new() -> UnionFindMap<T> {
+        UnionFindMap { index: HashMap::new(), elems: Vec::new(), parts: Vec::new(), rank: Vec::new() }
+    }
+
+    /// Insert `x` as a singleton partition if it has not
+    /// already been seen. Takes `x` by value so the insert
+    /// path moves it into element storage; the index key is
+    /// a clone of `x`. Running time O(1) amortized.
+    pub fn add(&mut self, x: T) {
+        if self.index.contains_key(&x) {
+            return;
+        }
+        let idx = self.elems.len();
+        self.index.insert(x.clone(), idx);
+        self.parts.push(idx);
+        self.rank.push(0);
+        self.elems.push(x);
+    }
+
+    /// Return the dense slot for `x`, inserting it as a new
+    /// singleton partition if it is unseen.
+    fn slot(&mut self, x: &T) -> usize {
+        if let Some(&idx) = self.index.get(x) {
+            return idx;
+        }
+        let idx = self.elems.len();
+        self.elems.push(x.clone());
+        self.parts.push(idx);
+        self.rank.push(0);
+        self.index.insert(x.clone(), idx);
+        idx
+    }
+
+    /// Find the root slot for `idx`, compressing the path
+    /// from `idx` to the root.
+    fn find_slot(&mut self, idx: usize) -> usize {
+        let mut p = idx;
+        while self.parts[p] != p {
+            p = self.parts[p]
+        };
+        let mut s = idx;
+        while s != p {
+            let t = self.parts[s];
+            self.parts[s] = p;
+            s = t
+        };
+        p
+    }
+
+    /// Find the root slot for `idx` without mutating the
+    /// table. Worst-case running time O(n); prefer
+    /// `find_slot()` when a mutable reference is available.
+    fn find_slot_only(&self, idx: usize) -> usize {
+        let mut p = idx;
+        while self.parts[p] != p {
+            p = self.parts[p]
+        };
+        p
+    }
+
+    /// Merge the partitions containing `i` and `j` by rank,
+    /// inserting either as a new singleton if unseen. Running
+    /// time O(alpha(n)) amortized.
+    pub fn union(&mut self, i: &T, j: &T) {
+        let ri = self.slot(i);
+        let rj = self.slot(j);
+        let ri = self.find_slot(ri);
+        let rj = self.find_slot(rj);
+        if ri == rj {
+            return;
+        }
+        let (small, big) = if self.rank[ri] < self.rank[rj] {
+            (ri, rj)
+        } else {
+            (rj, ri)
+        };
+        self.parts[small] = big;
+        if self.rank[ri] == self.rank[rj] {
+            self.rank[big] += 1;
+        }
+    }
+
+    /// Return a "canonical element" for the partition
+    /// containing `x`, inserting `x` as a new singleton if
+    /// unseen. Amortized running time O(alpha(n)).
+    pub fn find(&mut self, x: &T) -> T {
+        let idx = self.slot(x);
+        let root = self.find_slot(idx);
+        self.elems[root].clone()
+    }
+
+    /// Return a "canonical element" for the partition
+    /// containing `x`, or `None` if `x` has never been seen.
+    /// Unlike `find()`, this never inserts `x`. Worst-case
+    /// running time O(n).
+    pub fn find_checked(&self, x: &T) -> Option<T> {
+        let &idx = self.index.get(x)?;
+        let root = self.find_slot_only(idx);
+        Some(self.elems[root].clone())
+    }
+
+    /// Return `true` iff `i` and `j` are in the same
+    /// partition, inserting either as a new singleton if
+    /// unseen. Time complexity is the same as `find()`.
+    pub fn same(&mut self, i: &T, j: &T) -> bool {
+        self.find(i) == self.find(j)
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for UnionFindMap<T> {
+    fn default() -> UnionFindMap<T> {
+        UnionFindMap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Depth of `i` in its tree: the number of raw parent
+    /// links from `i` up to `find_only(i)`, walked without
+    /// path compression.
+    fn depth(uf: &UnionFind, i: usize) -> usize {
+        let mut p = i;
+        let mut d = 0;
+        while uf.parts[p] != p {
+            p = uf.parts[p];
+            d += 1;
+        }
+        d
+    }
+
+    #[test]
+    fn union_balances_by_rank() {
+        let mut uf = UnionFind::new(16);
+        // Union a chain of 16 elements pairwise; naive
+        // "attach j under i" union would build a tree of
+        // depth 15, but rank-balanced union should keep the
+        // tree logarithmic in the number of elements merged.
+        for i in 0..15 {
+            uf.union(i, i + 1);
+        }
+        for i in 0..16 {
+            assert!(depth(&uf, i) <= 4, "element {} at depth {}", i, depth(&uf, i));
+        }
+        assert_eq!(uf.size(0), 16);
+    }
+
+    #[test]
+    fn union_into_preserves_old_canonical_of_i() {
+        let mut uf = UnionFind::new(10);
+        let ci = uf.find(3);
+        uf.union_into(3, 7);
+        assert_eq!(uf.find(3), ci);
+        assert_eq!(uf.find(7), ci);
+        assert_eq!(uf.size(3), 2);
+    }
+
+    #[test]
+    fn size_tracks_partition_growth() {
+        let mut uf = UnionFind::new(5);
+        for i in 0..5 {
+            assert_eq!(uf.size(i), 1);
+        }
+        uf.union(0, 1);
+        uf.union(2, 3);
+        uf.union(1, 2);
+        assert_eq!(uf.size(0), 4);
+        assert_eq!(uf.size(4), 1);
+    }
+
+    fn sorted_groups(mut groups: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        for g in &mut groups {
+            g.sort();
+        }
+        groups.sort();
+        groups
+    }
+
+    #[test]
+    fn subsets_bucket_by_partition() {
+        let mut uf = UnionFind::new(6);
+        uf.union(0, 1);
+        uf.union(1, 2);
+        uf.union(4, 5);
+        assert_eq!(
+            sorted_groups(uf.subsets()),
+            sorted_groups(vec![vec![0, 1, 2], vec![3], vec![4, 5]])
+        );
+        assert_eq!(
+            sorted_groups(uf.nontrivial_subsets()),
+            sorted_groups(vec![vec![0, 1, 2], vec![4, 5]])
+        );
+    }
+
+    #[test]
+    fn count_matches_number_of_partitions() {
+        let mut uf = UnionFind::new(6);
+        assert_eq!(uf.count(), 6);
+        uf.union(0, 1);
+        uf.union(1, 2);
+        assert_eq!(uf.count(), 4);
+        uf.union(4, 5);
+        assert_eq!(uf.count(), 3);
+    }
+
+    #[test]
+    fn into_iter_matches_subsets() {
+        let mut uf = UnionFind::new(4);
+        uf.union(0, 1);
+        let via_subsets = sorted_groups(uf.subsets());
+        let via_iter = sorted_groups((&mut uf).into_iter().collect());
+        assert_eq!(via_subsets, via_iter);
+    }
+
+    #[test]
+    fn interval_mode_tracks_next_free() {
+        let mut uf = UnionFind::new_interval(5);
+        assert_eq!(uf.next_free(0), 0);
+        // Consume out of order; next_free() should still
+        // report the smallest untouched index.
+        uf.consume(2);
+        uf.consume(0);
+        uf.consume(1);
+        assert_eq!(uf.next_free(0), 3);
+        assert_eq!(uf.next_free(3), 3);
+        uf.consume(3);
+        uf.consume(4);
+        assert_eq!(uf.next_free(0), 5);
+    }
+
+    #[test]
+    fn consume_on_sentinel_is_a_no_op() {
+        let mut uf = UnionFind::new_interval(2);
+        uf.consume(0);
+        uf.consume(1);
+        assert_eq!(uf.next_free(0), 2);
+        uf.consume(2);
+        assert_eq!(uf.next_free(0), 2);
+        assert_eq!(uf.next_free(2), 2);
+    }
+
+    #[test]
+    fn weighted_union_with_independent_chains_and_conflict() {
+        let mut wuf = WeightedUnionFind::new(6);
+        // Two separate constraint chains, not yet related.
+        assert!(wuf.union_with(0, 1, 2));
+        assert!(wuf.union_with(1, 2, 3));
+        assert!(wuf.union_with(3, 4, 5));
+        assert_eq!(wuf.diff(0, 2), Some(5));
+        assert_eq!(wuf.diff(3, 4), Some(5));
+        // Unrelated elements (different roots, or never
+        // touched) report no difference.
+        assert_eq!(wuf.diff(0, 3), None);
+        assert_eq!(wuf.diff(5, 0), None);
+        // Merge the two chains with a consistent constraint.
+        assert!(wuf.union_with(2, 3, 1));
+        assert_eq!(wuf.diff(0, 4), Some(2 + 3 + 1 + 5));
+        // A conflicting constraint on already-related elements
+        // is rejected and leaves the table unchanged.
+        assert!(!wuf.union_with(0, 4, 0));
+        assert_eq!(wuf.diff(0, 4), Some(2 + 3 + 1 + 5));
+    }
+
+    #[test]
+    fn weighted_union_with_forces_low_rank_attach_branch() {
+        let mut wuf = WeightedUnionFind::new(4);
+        // Build a rank-1 root over {1, 2, 3}...
+        assert!(wuf.union_with(1, 2, 3));
+        assert!(wuf.union_with(1, 3, 5));
+        // ...then merge in the still-rank-0 root of {0}, which
+        // forces the `rank[ri] < rank[rj]` branch in
+        // `union_with` and its `-delta`/`weight[ri]` attach.
+        assert!(wuf.union_with(0, 1, 2));
+        assert_eq!(wuf.diff(0, 1), Some(2));
+        assert_eq!(wuf.diff(0, 2), Some(5));
+        assert_eq!(wuf.diff(0, 3), Some(7));
+    }
+
+    #[test]
+    fn union_find_map_merges_multi_element_groups() {
+        let mut uf = UnionFindMap::new();
+        uf.union(&"a", &"b");
+        uf.union(&"c", &"d");
+        assert!(!uf.same(&"a", &"c"));
+        uf.union(&"b", &"c");
+        assert!(uf.same(&"a", &"d"));
+        assert!(uf.same(&"b", &"c"));
+    }
+
+    #[test]
+    fn union_find_map_find_checked_after_union() {
+        let mut uf = UnionFindMap::new();
+        assert_eq!(uf.find_checked(&"x"), None);
+        uf.union(&"x", &"y");
+        let canonical = uf.find(&"x");
+        assert_eq!(uf.find_checked(&"x"), Some(canonical));
+        assert_eq!(uf.find_checked(&"y"), Some(canonical));
+    }
+
+    #[test]
+    fn union_find_map_add_is_idempotent() {
+        let mut uf = UnionFindMap::new();
+        uf.add("p");
+        assert_eq!(uf.elems.len(), 1);
+        uf.add("p");
+        assert_eq!(uf.elems.len(), 1);
+        assert_eq!(uf.find(&"p"), "p");
+    }
 }